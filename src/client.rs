@@ -0,0 +1,318 @@
+//! Client module - sends notifications to APNs over HTTP/2.
+
+use crate::error::Error;
+use crate::response::{ErrorBody, Response};
+use hyper::{Body, Client as HyperClient, Request};
+use log::debug;
+use serde::Serialize;
+use std::io::Read;
+use std::time::Duration;
+
+#[cfg(not(feature = "rustls"))]
+mod connector {
+    use crate::error::Error;
+    use hyper::client::HttpConnector;
+    use hyper_openssl::HttpsConnector;
+    use openssl::pkey::PKey;
+    use openssl::ssl::{SslConnector, SslMethod};
+    use openssl::x509::X509;
+    use std::io::Read;
+
+    pub type Connector = HttpsConnector<HttpConnector>;
+
+    /// Builds the OpenSSL-backed connector from a PEM-encoded certificate and
+    /// private key, the password unlocking the key if it's encrypted.
+    pub fn from_pem<R: Read>(mut pem: R, password: &str) -> Result<Connector, Error> {
+        let mut bytes = Vec::new();
+        pem.read_to_end(&mut bytes)?;
+
+        let cert = X509::from_pem(&bytes)?;
+        let key = PKey::private_key_from_pem_passphrase(&bytes, password.as_bytes())?;
+
+        let mut ssl = SslConnector::builder(SslMethod::tls())?;
+        ssl.set_certificate(&cert)?;
+        ssl.set_private_key(&key)?;
+
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+
+        Ok(HttpsConnector::with_connector(http, ssl)?)
+    }
+}
+
+#[cfg(feature = "rustls")]
+mod connector {
+    use crate::error::Error;
+    use hyper::client::connect::{Connected, Connection, HttpConnector};
+    use hyper::service::Service;
+    use hyper::Uri;
+    use rustls::{Certificate, ClientConfig, PrivateKey};
+    use rustls_pemfile::{certs, pkcs8_private_keys};
+    use std::future::Future;
+    use std::io::{self, Read};
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWrite};
+    use tokio_rustls::{client::TlsStream, webpki::DNSNameRef, TlsConnector};
+
+    pub type Connector = RustlsConnector;
+
+    /// Builds the rustls-backed connector from a PEM-encoded certificate and
+    /// unencrypted PKCS8 private key. Unlike the OpenSSL path, `rustls-pemfile`
+    /// can't decrypt an encrypted key, so `password` is unused here.
+    pub fn from_pem<R: Read>(mut pem: R, _password: &str) -> Result<Connector, Error> {
+        let mut bytes = Vec::new();
+        pem.read_to_end(&mut bytes)?;
+
+        let cert_chain = certs(&mut bytes.as_slice())
+            .map_err(|_| Error::ReadError(io::Error::new(io::ErrorKind::InvalidData, "no certificate found in PEM")))?
+            .into_iter()
+            .map(Certificate)
+            .collect();
+
+        let key = pkcs8_private_keys(&mut bytes.as_slice())
+            .ok()
+            .and_then(|mut keys| keys.pop())
+            .map(PrivateKey)
+            .ok_or_else(|| {
+                Error::ReadError(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "no unencrypted PKCS8 private key found in PEM",
+                ))
+            })?;
+
+        let mut config = ClientConfig::new();
+        config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        config.set_single_client_cert(cert_chain, key)?;
+
+        Ok(RustlsConnector::new(config))
+    }
+
+    #[derive(Clone)]
+    pub struct RustlsConnector {
+        http: HttpConnector,
+        tls: TlsConnector,
+    }
+
+    impl RustlsConnector {
+        fn new(config: ClientConfig) -> Self {
+            let mut http = HttpConnector::new();
+            http.enforce_http(false);
+
+            RustlsConnector {
+                http,
+                tls: TlsConnector::from(Arc::new(config)),
+            }
+        }
+    }
+
+    /// A TCP stream wrapped in a TLS session, implementing the traits hyper
+    /// needs from a connector's output.
+    pub struct TlsConnection(TlsStream<tokio::net::TcpStream>);
+
+    impl AsyncRead for TlsConnection {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut Pin::into_inner(self).0).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for TlsConnection {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut Pin::into_inner(self).0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut Pin::into_inner(self).0).poll_flush(cx)
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut Pin::into_inner(self).0).poll_shutdown(cx)
+        }
+    }
+
+    impl Connection for TlsConnection {
+        fn connected(&self) -> Connected {
+            Connected::new()
+        }
+    }
+
+    impl Service<Uri> for RustlsConnector {
+        type Response = TlsConnection;
+        type Error = io::Error;
+        type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Service::<Uri>::poll_ready(&mut self.http, cx).map_err(io::Error::other)
+        }
+
+        fn call(&mut self, uri: Uri) -> Self::Future {
+            let tls = self.tls.clone();
+            let host = uri.host().unwrap_or_default().to_string();
+            let mut http = self.http.clone();
+
+            Box::pin(async move {
+                let tcp = Service::<Uri>::call(&mut http, uri)
+                    .await
+                    .map_err(io::Error::other)?;
+
+                let dns_name = DNSNameRef::try_from_ascii_str(&host)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+                let stream = tls.connect(dns_name, tcp).await?;
+
+                Ok(TlsConnection(stream))
+            })
+        }
+    }
+}
+
+/// Which APNs environment to send notifications to.
+#[derive(Debug, Clone, Copy)]
+pub enum Endpoint {
+    /// The production APNs environment.
+    Production,
+
+    /// The sandbox environment used by apps signed for development/TestFlight.
+    Sandbox,
+}
+
+impl Endpoint {
+    fn url(self) -> &'static str {
+        match self {
+            Endpoint::Production => "https://api.push.apple.com",
+            Endpoint::Sandbox => "https://api.sandbox.push.apple.com",
+        }
+    }
+}
+
+/// Sends push notifications to APNs over a certificate-authenticated HTTP/2
+/// connection.
+pub struct Client {
+    http: HyperClient<connector::Connector, Body>,
+    endpoint: Endpoint,
+}
+
+impl Client {
+    /// Creates a client authenticating with a PEM-encoded certificate and
+    /// private key, e.g. converted from the `.p12` Apple provides via
+    /// `openssl pkcs12`.
+    pub fn certificate<R: Read>(pem: R, password: &str, endpoint: Endpoint) -> Result<Client, Error> {
+        let connector = connector::from_pem(pem, password)?;
+        let http = HyperClient::builder().http2_only(true).build(connector);
+
+        Ok(Client { http, endpoint })
+    }
+
+    /// Serializes and sends a single notification, returning as soon as APNs
+    /// has responded.
+    pub async fn send<T: Serialize>(&self, device_token: &str, payload: &T) -> Result<Response, Error> {
+        let body = serde_json::to_vec(payload)?;
+        debug!(
+            "Dispatching a notification ({} bytes) to device {}",
+            body.len(),
+            device_token
+        );
+
+        let uri = format!("{}/3/device/{}", self.endpoint.url(), device_token);
+
+        let request = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .expect("request built from a validated URI and static headers");
+
+        let response = self.http.request(request).await?;
+        self.parse_response(response).await
+    }
+
+    /// Sends a notification, retrying transient failures
+    /// ([is_retryable](../error/enum.Error.html#method.is_retryable)) and
+    /// making at most `max_attempts` attempts in all (at least one), then
+    /// returning the last error. Honors APNs' `retry-after` header when
+    /// present, otherwise backs off exponentially with jitter between attempts.
+    pub async fn send_with_retry<T: Serialize>(
+        &self,
+        device_token: &str,
+        payload: &T,
+        max_attempts: u32,
+    ) -> Result<Response, Error> {
+        let mut attempt = 1;
+
+        loop {
+            match self.send(device_token, payload).await {
+                Ok(response) => return Ok(response),
+                Err(e) if attempt < max_attempts && e.is_retryable() => {
+                    let delay = e.retry_after().unwrap_or_else(|| backoff_with_jitter(attempt));
+
+                    debug!(
+                        "Retrying a rejected notification in {:?} (attempt {} of {})",
+                        delay, attempt, max_attempts
+                    );
+
+                    tokio::time::delay_for(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn parse_response(&self, response: hyper::Response<Body>) -> Result<Response, Error> {
+        let code = response.status().as_u16();
+
+        let apns_id = response
+            .headers()
+            .get("apns-id")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        let retry_after = response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs);
+
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+
+        let error = if body.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_slice::<ErrorBody>(&body)?)
+        };
+
+        let response = Response {
+            code,
+            apns_id,
+            error,
+            retry_after,
+        };
+
+        if response.error.is_some() {
+            Err(response.into())
+        } else {
+            Ok(response)
+        }
+    }
+}
+
+/// Exponential backoff with jitter, used when APNs doesn't tell us how long
+/// to wait before retrying.
+fn backoff_with_jitter(attempt: u32) -> Duration {
+    let base_ms = 200u64.saturating_mul(1u64 << attempt.min(6));
+    let jitter_ms = rand::random::<u64>() % 250;
+
+    Duration::from_millis(base_ms + jitter_ms)
+}
@@ -1,26 +1,36 @@
 //! Error and result module
 
 use crate::response::{ErrorBody, Response};
+use log::{error, warn};
+#[cfg(not(feature = "rustls"))]
 use openssl::error::ErrorStack;
 use serde_json::Error as SerdeError;
-use std::convert::From;
 use std::error::Error as StdError;
 use std::fmt;
 use std::io::Error as IoError;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub enum Error {
     /// User request or Apple response JSON data was faulty.
-    SerializeError,
+    SerializeError(SerdeError),
 
     /// A problem connecting to APNs servers.
-    ConnectionError,
+    ConnectionError(hyper::error::Error),
 
     /// APNs couldn't response in a timely manner, if using
     /// [send_with_timeout](client/struct.Client.html#method.send_with_timeout)
     TimeoutError,
 
     /// Couldn't generate an APNs token with the given key.
+    #[cfg(not(feature = "rustls"))]
+    SignerError(ErrorStack),
+
+    /// Couldn't generate an APNs token with the given key. Holds a formatted
+    /// message rather than `ring`'s key/signing error types, since neither
+    /// `ring::error::KeyRejected` nor `ring::error::Unspecified` implements
+    /// `std::error::Error`.
+    #[cfg(feature = "rustls")]
     SignerError(String),
 
     /// APNs couldn't accept the notification. Contains
@@ -36,60 +46,213 @@ pub enum Error {
     TlsError(String),
 
     /// Error reading the certificate or private key.
-    ReadError(String),
+    ReadError(IoError),
+}
+
+/// The documented set of reasons APNs gives for rejecting a notification, as
+/// carried in [ErrorBody::reason](response/struct.ErrorBody.html#structfield.reason).
+/// Unrecognized reasons (Apple adds new ones over time) fall back to `Other`
+/// rather than failing to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    PayloadEmpty,
+    PayloadTooLarge,
+    BadTopic,
+    TopicDisallowed,
+    BadMessageId,
+    BadExpirationDate,
+    BadPriority,
+    MissingDeviceToken,
+    BadDeviceToken,
+    DeviceTokenNotForTopic,
+    Unregistered,
+    DuplicateHeaders,
+    BadCertificateEnvironment,
+    BadCertificate,
+    Forbidden,
+    BadPath,
+    MethodNotAllowed,
+    ExpiredProviderToken,
+    MissingProviderToken,
+    InvalidProviderToken,
+    MissingTopic,
+    TooManyProviderTokenUpdates,
+    TooManyRequests,
+    InternalServerError,
+    ServiceUnavailable,
+    Shutdown,
+    Other(String),
 }
 
-impl<'a> fmt::Display for Error {
+impl Reason {
+    /// Whether a rejection for this reason is transient, i.e. worth retrying
+    /// the same notification rather than treating it as a permanent failure.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Reason::TooManyRequests
+                | Reason::InternalServerError
+                | Reason::ServiceUnavailable
+                | Reason::Shutdown
+        )
+    }
+}
+
+impl From<&str> for Reason {
+    fn from(reason: &str) -> Reason {
+        match reason {
+            "PayloadEmpty" => Reason::PayloadEmpty,
+            "PayloadTooLarge" => Reason::PayloadTooLarge,
+            "BadTopic" => Reason::BadTopic,
+            "TopicDisallowed" => Reason::TopicDisallowed,
+            "BadMessageId" => Reason::BadMessageId,
+            "BadExpirationDate" => Reason::BadExpirationDate,
+            "BadPriority" => Reason::BadPriority,
+            "MissingDeviceToken" => Reason::MissingDeviceToken,
+            "BadDeviceToken" => Reason::BadDeviceToken,
+            "DeviceTokenNotForTopic" => Reason::DeviceTokenNotForTopic,
+            "Unregistered" => Reason::Unregistered,
+            "DuplicateHeaders" => Reason::DuplicateHeaders,
+            "BadCertificateEnvironment" => Reason::BadCertificateEnvironment,
+            "BadCertificate" => Reason::BadCertificate,
+            "Forbidden" => Reason::Forbidden,
+            "BadPath" => Reason::BadPath,
+            "MethodNotAllowed" => Reason::MethodNotAllowed,
+            "ExpiredProviderToken" => Reason::ExpiredProviderToken,
+            "MissingProviderToken" => Reason::MissingProviderToken,
+            "InvalidProviderToken" => Reason::InvalidProviderToken,
+            "MissingTopic" => Reason::MissingTopic,
+            "TooManyProviderTokenUpdates" => Reason::TooManyProviderTokenUpdates,
+            "TooManyRequests" => Reason::TooManyRequests,
+            "InternalServerError" => Reason::InternalServerError,
+            "ServiceUnavailable" => Reason::ServiceUnavailable,
+            "Shutdown" => Reason::Shutdown,
+            other => Reason::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Other(reason) => write!(fmt, "{}", reason),
+            reason => write!(fmt, "{:?}", reason),
+        }
+    }
+}
+
+impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Error::SerializeError(e) => write!(fmt, "Error serializing to JSON: {}", e),
+            Error::ConnectionError(e) => write!(fmt, "Error connecting to APNs: {}", e),
+            Error::TimeoutError => write!(fmt, "Timeout in sending a push notification"),
+            Error::SignerError(e) => write!(fmt, "Error creating a signature: {}", e),
+            Error::ResponseError(Response {
+                error: Some(ErrorBody { ref reason, .. }),
+                ..
+            }) => write!(
+                fmt,
+                "Notification was not accepted by APNs (reason: {})",
+                Reason::from(reason.as_str())
+            ),
+            Error::ResponseError(_) => write!(fmt, "Notification was not accepted by APNs"),
+            Error::InvalidOptions(ref message) => {
+                write!(fmt, "Invalid options for APNs payload: {}", message)
+            }
+            Error::TlsError(ref message) => {
+                write!(fmt, "Error in creating a TLS connection: {}", message)
+            }
+            Error::ReadError(e) => write!(fmt, "Error in reading a certificate file: {}", e),
+        }
+    }
+}
+
+impl Error {
+    /// Whether the failure is transient and the same notification is worth
+    /// sending again, e.g. with
+    /// [send_with_retry](client/struct.Client.html#method.send_with_retry).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::ConnectionError(_) | Error::TimeoutError => true,
             Error::ResponseError(Response {
                 error: Some(ErrorBody { ref reason, .. }),
                 ..
-            }) => write!(fmt, "{} (reason: {:?})", self, reason),
-            _ => write!(fmt, "{}", self),
+            }) => Reason::from(reason.as_str()).is_retryable(),
+            _ => false,
+        }
+    }
+
+    /// The delay APNs asked for before retrying, taken from the response's
+    /// `retry-after` header, if this is a [ResponseError](#variant.ResponseError).
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::ResponseError(Response { retry_after, .. }) => *retry_after,
+            _ => None,
         }
     }
 }
 
-impl<'a> StdError for Error {
-    fn description(&self) -> &str {
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
-            Error::SerializeError => "Error serializing to JSON",
-            Error::ConnectionError => "Error connecting to APNs",
-            Error::SignerError(_) => "Error creating a signature",
-            Error::ResponseError(_) => "Notification was not accepted by APNs",
-            Error::InvalidOptions(_) => "Invalid options for APNs payload",
-            Error::TlsError(_) => "Error in creating a TLS connection",
-            Error::ReadError(_) => "Error in reading a certificate file",
-            Error::TimeoutError => "Timeout in sending a push notification",
+            Error::SerializeError(e) => Some(e),
+            Error::ConnectionError(e) => Some(e),
+            #[cfg(not(feature = "rustls"))]
+            Error::SignerError(e) => Some(e),
+            Error::ReadError(e) => Some(e),
+            _ => None,
         }
     }
+}
+
+impl From<Response> for Error {
+    fn from(response: Response) -> Error {
+        if let Some(ErrorBody { ref reason, .. }) = response.error {
+            warn!(
+                "APNs rejected notification (status {}): {}",
+                response.code,
+                Reason::from(reason.as_str())
+            );
+        }
 
-    fn cause(&self) -> Option<&dyn StdError> {
-        None
+        Error::ResponseError(response)
     }
 }
 
 impl From<SerdeError> for Error {
-    fn from(_: SerdeError) -> Error {
-        Error::SerializeError
+    fn from(e: SerdeError) -> Error {
+        error!("Error (de)serializing JSON for an APNs request or response: {}", e);
+        Error::SerializeError(e)
     }
 }
 
+#[cfg(not(feature = "rustls"))]
 impl From<ErrorStack> for Error {
     fn from(e: ErrorStack) -> Error {
-        Error::SignerError(format!("{}", e))
+        Error::SignerError(e)
     }
 }
 
 impl From<IoError> for Error {
     fn from(e: IoError) -> Error {
-        Error::ReadError(format!("{}", e))
+        Error::ReadError(e)
     }
 }
 
 impl From<hyper::error::Error> for Error {
-    fn from(_: hyper::error::Error) -> Error {
-        Error::ConnectionError
+    fn from(e: hyper::error::Error) -> Error {
+        error!("Error connecting to APNs: {}", e);
+        Error::ConnectionError(e)
+    }
+}
+
+/// Backend-neutral TLS error conversion for the `rustls` connector, mirroring
+/// the OpenSSL path above.
+#[cfg(feature = "rustls")]
+impl From<rustls::TLSError> for Error {
+    fn from(e: rustls::TLSError) -> Error {
+        error!("Error in creating a TLS connection: {}", e);
+        Error::TlsError(format!("{}", e))
     }
 }
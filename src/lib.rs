@@ -0,0 +1,9 @@
+//! An asynchronous client for sending push notifications to Apple devices
+//! through APNs.
+
+pub mod client;
+pub mod error;
+pub mod response;
+
+pub use crate::client::{Client, Endpoint};
+pub use crate::error::Error;
@@ -0,0 +1,33 @@
+//! Response module
+
+use serde::Deserialize;
+use std::time::Duration;
+
+/// What APNs sent back after receiving a notification.
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// The HTTP status code APNs responded with.
+    pub code: u16,
+
+    /// The `apns-id` APNs assigned to the notification, echoed back from the
+    /// response headers.
+    pub apns_id: Option<String>,
+
+    /// Present when APNs rejected the notification.
+    pub error: Option<ErrorBody>,
+
+    /// The `retry-after` header APNs sends alongside a transient rejection,
+    /// if present.
+    pub retry_after: Option<Duration>,
+}
+
+/// The JSON body APNs sends back when it rejects a notification.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorBody {
+    /// The reason for the rejection, e.g. `"BadDeviceToken"`.
+    pub reason: String,
+
+    /// Present only when `reason` is `"Unregistered"`, the time the device
+    /// token became invalid, in milliseconds since the epoch.
+    pub timestamp: Option<i64>,
+}